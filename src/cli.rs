@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use crate::report::{Format, GroupBy};
+
+#[derive(clap::Parser)]
+#[command(name = "timetrackd", about = "Tracks active-window time and reports on it")]
+pub struct Cli {
+    /// Path to the config file, instead of $XDG_CONFIG_HOME/timetrackd.toml
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Run the sampling daemon
+    Run,
+    /// Query the database and print aggregated time per application/category
+    Report(ReportArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ReportArgs {
+    /// Only include intervals that end on or after the start of this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Only include intervals that start on or before the end of this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<String>,
+    /// How to group reported time
+    #[arg(long, value_enum, default_value = "process")]
+    pub by: GroupByArg,
+    /// Exclude idle/screensaver intervals from the totals
+    #[arg(long)]
+    pub exclude_idle: bool,
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: FormatArg,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum GroupByArg {
+    Process,
+    Category,
+    Day,
+}
+
+impl From<GroupByArg> for GroupBy {
+    fn from(arg: GroupByArg) -> GroupBy {
+        return match arg {
+            GroupByArg::Process => GroupBy::Process,
+            GroupByArg::Category => GroupBy::Category,
+            GroupByArg::Day => GroupBy::Day,
+        };
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum FormatArg {
+    Table,
+    Json,
+    Csv,
+}
+
+impl From<FormatArg> for Format {
+    fn from(arg: FormatArg) -> Format {
+        return match arg {
+            FormatArg::Table => Format::Table,
+            FormatArg::Json => Format::Json,
+            FormatArg::Csv => Format::Csv,
+        };
+    }
+}
@@ -0,0 +1,53 @@
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Abstracts wall-clock time and sleeping, the way moonfire-nvr's `Clocks`
+// trait does, so the main loop can be driven with injected time in tests
+// instead of depending on the real clock and `thread::sleep`.
+pub trait Clocks {
+    fn now(&self) -> u64;
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct RealClocks;
+
+impl Clocks for RealClocks {
+    fn now(&self) -> u64 {
+        return SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before UNIX_EPOCH")
+            .as_secs();
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// A clock that never sleeps for real and whose time only advances when
+// `advance` is called, so tests can step the main loop deterministically.
+pub struct FakeClocks {
+    now: std::cell::Cell<u64>,
+}
+
+impl FakeClocks {
+    pub fn new(start: u64) -> FakeClocks {
+        return FakeClocks {
+            now: std::cell::Cell::new(start),
+        };
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration.as_secs());
+    }
+}
+
+impl Clocks for FakeClocks {
+    fn now(&self) -> u64 {
+        return self.now.get();
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use dbus::blocking::Connection;
+use x11rb::rust_connection::RustConnection;
+
+use crate::sampler::SampleError;
+
+impl From<dbus::Error> for SampleError {
+    fn from(err: dbus::Error) -> SampleError {
+        return SampleError::Dbus(err);
+    }
+}
+
+impl From<x11rb::errors::ConnectError> for SampleError {
+    fn from(err: x11rb::errors::ConnectError) -> SampleError {
+        return SampleError::X11(err.to_string());
+    }
+}
+
+impl From<x11rb::errors::ConnectionError> for SampleError {
+    fn from(err: x11rb::errors::ConnectionError) -> SampleError {
+        return SampleError::X11(err.to_string());
+    }
+}
+
+impl From<x11rb::errors::ReplyError> for SampleError {
+    fn from(err: x11rb::errors::ReplyError) -> SampleError {
+        return SampleError::X11(err.to_string());
+    }
+}
+
+// `org.freedesktop.ScreenSaver` is the de-facto standard, but GNOME and KDE
+// shipped their own interfaces before it was widely adopted and still
+// register them today, so we try all three in order.
+const SCREENSAVER_SERVICES: &[(&str, &str, &str)] = &[
+    (
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+    ),
+    (
+        "org.gnome.ScreenSaver",
+        "/org/gnome/ScreenSaver",
+        "org.gnome.ScreenSaver",
+    ),
+    ("org.kde.screensaver", "/ScreenSaver", "org.freedesktop.ScreenSaver"),
+];
+
+const DBUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub fn screensaver_active(conn: &Connection) -> Result<bool, SampleError> {
+    for (destination, path, interface) in SCREENSAVER_SERVICES {
+        let proxy = conn.with_proxy(*destination, *path, DBUS_TIMEOUT);
+        let result: Result<(bool,), dbus::Error> =
+            proxy.method_call(*interface, "GetActive", ());
+        if let Ok((active,)) = result {
+            return Ok(active);
+        }
+    }
+    return Err(SampleError::NoIdleSource);
+}
+
+fn idle_millis_mutter(conn: &Connection) -> Result<u64, SampleError> {
+    let proxy = conn.with_proxy(
+        "org.gnome.Mutter.IdleMonitor",
+        "/org/gnome/Mutter/IdleMonitor/Core",
+        DBUS_TIMEOUT,
+    );
+    let (ms,): (u64,) =
+        proxy.method_call("org.gnome.Mutter.IdleMonitor", "GetIdletime", ())?;
+    return Ok(ms);
+}
+
+// The X11 connection is opened at most once and reused for the life of the
+// process; reconnecting on every tick would mean one round-trip to the X
+// server per sample just to check idle time.
+static X11_CONNECTION: OnceLock<(RustConnection, usize)> = OnceLock::new();
+
+fn x11_connection() -> Result<&'static (RustConnection, usize), SampleError> {
+    if let Some(conn) = X11_CONNECTION.get() {
+        return Ok(conn);
+    }
+    let connected = x11rb::connect(None)?;
+    // Another caller may have raced us to initialize it; either way, by the
+    // time set() returns, get() is guaranteed to return a connection.
+    let _ = X11_CONNECTION.set(connected);
+    return Ok(X11_CONNECTION.get().expect("just initialized above"));
+}
+
+fn idle_millis_x11_screensaver_extension() -> Result<u64, SampleError> {
+    let (conn, screen_num) = x11_connection()?;
+    let screen = &conn.setup().roots[*screen_num];
+    let info = x11rb::protocol::screensaver::query_info(conn, screen.root)?.reply()?;
+    return Ok(info.ms_since_user_input as u64);
+}
+
+// Tries the GNOME Mutter idle monitor first (it is what most Wayland and
+// modern GNOME/X11 sessions expose), then falls back to the X11 screensaver
+// extension, which works on plain X11 desktops without Mutter. Returns
+// `NoIdleSource` rather than either source's raw error, since by this point
+// we can no longer tell whether Mutter is merely absent or actually failing.
+pub fn idle_millis(conn: &Connection) -> Result<u64, SampleError> {
+    if let Ok(ms) = idle_millis_mutter(conn) {
+        return Ok(ms);
+    }
+    if let Ok(ms) = idle_millis_x11_screensaver_extension() {
+        return Ok(ms);
+    }
+    return Err(SampleError::NoIdleSource);
+}
@@ -1,75 +1,33 @@
 use std::io;
 use std::fs;
 use std::path;
-use std::process::Command;
-use std::{thread, time};
+use std::time;
 
+extern crate chrono;
+extern crate clap;
+extern crate dbus;
 extern crate dirs;
+extern crate regex;
+extern crate rusqlite;
+extern crate serde;
+extern crate serde_json;
 extern crate toml;
+extern crate x11rb;
 
-#[derive(PartialEq, Clone)]
-struct Sample {
-    window_title: String,
-    pid: u32,
-    process_name: String,
-    screensaver_active: bool,
-    idle: bool,
-}
-
-#[derive(Debug)]
-enum SampleError {
-    Io(io::Error),
-    FromUtf8(std::string::FromUtf8Error),
-    ParseInt(std::num::ParseIntError),
-}
-
-impl From<io::Error> for SampleError {
-    fn from(err: io::Error) -> SampleError {
-        return SampleError::Io(err);
-    }
-}
-
-impl From<std::string::FromUtf8Error> for SampleError {
-    fn from(err: std::string::FromUtf8Error) -> SampleError {
-        return SampleError::FromUtf8(err);
-    }
-}
-
-impl From<std::num::ParseIntError> for SampleError {
-    fn from(err: std::num::ParseIntError) -> SampleError {
-        return SampleError::ParseInt(err);
-    }
-}
-
-fn get_command_output(command: &str, args: &[&str]) -> Result<String, SampleError> {
-    return Ok(String::from_utf8(
-        Command::new(command)
-            .args(args)
-            .env("LC_ALL", "C") // I will never understand localization of CLI tool output
-            .output()?
-            .stdout,
-    )?
-    .trim()
-    .to_string());
-}
+mod cli;
+mod clock;
+mod idle;
+mod report;
+mod rules;
+mod sampler;
+mod storage;
 
-fn get_sample(sample_interval: &std::time::Duration) -> Result<Sample, SampleError> {
-    let window_title = get_command_output("xdotool", &["getactivewindow", "getwindowname"])?;
-    let pid_str = get_command_output("xdotool", &["getactivewindow", "getwindowpid"])?;
-    let pid: u32 = pid_str.parse()?;
-    let process_name = get_command_output("ps", &["-p", &pid_str, "-o", "comm="])?;
-    let screensaver_active =
-        !get_command_output("gnome-screensaver-command", &["-q"])?.contains("inactive");
-    let idle_time: u128 = get_command_output("xprintidle", &[])?.parse()?;
-    let idle = idle_time > sample_interval.as_millis();
-    return Ok(Sample {
-        window_title,
-        pid,
-        process_name,
-        screensaver_active,
-        idle,
-    });
-}
+use clap::Parser;
+use cli::{Cli, Command, ReportArgs};
+use clock::{Clocks, RealClocks};
+use rules::{categorize, Rule};
+use sampler::{Sample, Sampler, WaylandSampler, XdotoolSampler};
+use storage::{Db, Interval};
 
 #[derive(Debug)]
 enum LoadConfigError {
@@ -96,11 +54,28 @@ enum DatabaseType {
     Sqlite,
 }
 
+#[derive(Debug)]
+enum SamplerBackend {
+    Xdotool,
+    Wayland,
+}
+
+impl SamplerBackend {
+    fn build(&self) -> Box<dyn Sampler> {
+        return match self {
+            SamplerBackend::Xdotool => Box::new(XdotoolSampler),
+            SamplerBackend::Wayland => Box::new(WaylandSampler),
+        };
+    }
+}
+
 #[derive(Debug)]
 struct Config {
     database_path: path::PathBuf,
     database_type: DatabaseType,
+    sampler_backend: SamplerBackend,
     sample_interval: u64,
+    rules: Vec<Rule>,
 }
 
 impl Default for Config {
@@ -111,7 +86,9 @@ impl Default for Config {
                 None => panic!("Could not get home directory!")
             }.join(".timetrackd.db"),
             database_type: DatabaseType::Sqlite,
+            sampler_backend: SamplerBackend::Xdotool,
             sample_interval: 5,
+            rules: Vec::new(),
         }
     }
 }
@@ -140,11 +117,28 @@ fn parse_database_type(value: &toml::Value) -> Option<DatabaseType> {
     }
 }
 
-fn load_config() -> Result<Config, LoadConfigError> {
-    let config_path = match dirs::config_dir() {
-        Some(path) => path,
-        None => return Err(LoadConfigError::ConfigDirError)
-    }.join("timetrackd.toml");
+fn parse_sampler_backend(value: &toml::Value) -> Option<SamplerBackend> {
+    if !value.is_str() {
+        return None;
+    }
+    return match value.as_str().unwrap() {
+        "xdotool" => Some(SamplerBackend::Xdotool),
+        "wayland" => Some(SamplerBackend::Wayland),
+        _ => None
+    }
+}
+
+fn load_config(config_path: Option<&path::Path>) -> Result<Config, LoadConfigError> {
+    let (config_path, explicit) = match config_path {
+        Some(path) => (path.to_path_buf(), true),
+        None => {
+            let path = match dirs::config_dir() {
+                Some(path) => path,
+                None => return Err(LoadConfigError::ConfigDirError)
+            }.join("timetrackd.toml");
+            (path, false)
+        }
+    };
 
     let mut config = Config::default();
     if config_path.is_file() {
@@ -171,46 +165,246 @@ fn load_config() -> Result<Config, LoadConfigError> {
                 None => return Err(LoadConfigError::ParseError {0: "database_type must be 'sqlite'".to_string()})
             }
         }
+
+        if config_data.get("sampler_backend").is_some() {
+            config.sampler_backend = match parse_sampler_backend(&config_data["sampler_backend"]) {
+                Some(backend) => backend,
+                None => return Err(LoadConfigError::ParseError {0: "sampler_backend must be 'xdotool' or 'wayland'".to_string()})
+            }
+        }
+
+        if config_data.get("rule").is_some() {
+            config.rules = rules::parse_rules(&config_data["rule"])?;
+        }
+    } else if explicit {
+        return Err(LoadConfigError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("config file '{}' not found", config_path.to_string_lossy()),
+        )));
     } else {
         eprintln!("Could not load config file '{}'", config_path.to_string_lossy());
     }
     return Ok(config);
 }
 
+struct OpenInterval {
+    sample: Sample,
+    start_ts: u64,
+}
+
+fn close_interval(db: &mut Db, open: OpenInterval, end_ts: u64) {
+    let interval = Interval {
+        start_ts: open.start_ts,
+        end_ts,
+        duration: end_ts.saturating_sub(open.start_ts),
+        window_title: open.sample.window_title,
+        pid: open.sample.pid,
+        process_name: open.sample.process_name,
+        idle: open.sample.idle,
+        screensaver: open.sample.screensaver_active,
+        category: open.sample.category,
+    };
+    if let Err(err) = db.record_interval(&interval) {
+        eprintln!("Error writing interval to database: {:?}", err);
+    }
+}
+
 fn main() {
-    let config: Config = match load_config() {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Run => run_daemon(cli.config.as_deref()),
+        Command::Report(args) => run_report(cli.config.as_deref(), args),
+    }
+}
+
+fn run_daemon(config_path: Option<&path::Path>) {
+    let config: Config = match load_config(config_path) {
         Ok(config) => config,
         Err(err) => panic!("Could not load config: {:?}", err)
     };
     println!("Config: {:?}", config);
 
+    let mut db = match Db::open(&config.database_path) {
+        Ok(db) => db,
+        Err(err) => panic!("Could not open database '{}': {:?}", config.database_path.to_string_lossy(), err)
+    };
+
+    let sampler = config.sampler_backend.build();
+    let clocks = RealClocks;
     let sample_interval = time::Duration::from_secs(config.sample_interval);
-    let mut last_sample: Option<Sample> = None;
-    loop {
-        match get_sample(&sample_interval) {
-            Ok(sample) => {
-                if last_sample != Some(sample.clone()) {
-                    if sample.screensaver_active {
-                        println!("screensaver");
-                    } else {
-                        print!(
-                            "'{}' ([{}] {})",
-                            sample.window_title, sample.pid, sample.process_name
-                        );
-                        if sample.idle {
-                            println!(" (idle)");
-                        } else {
-                            println!("");
-                        }
-                    }
-                    last_sample = Some(sample);
-                }
+    run(sampler.as_ref(), &clocks, &mut db, sample_interval, &config.rules);
+}
+
+// `end_of_day` makes `--to` inclusive of the whole day it names, instead of
+// excluding everything but midnight.
+fn parse_date_arg(value: &str, end_of_day: bool) -> Result<u64, String> {
+    let date = match chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(err) => return Err(format!("'{}' is not a valid date (expected YYYY-MM-DD): {}", value, err))
+    };
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59).unwrap()
+    } else {
+        date.and_hms_opt(0, 0, 0).unwrap()
+    };
+    return Ok(time.and_utc().timestamp() as u64);
+}
+
+fn run_report(config_path: Option<&path::Path>, args: ReportArgs) {
+    let config: Config = match load_config(config_path) {
+        Ok(config) => config,
+        Err(err) => panic!("Could not load config: {:?}", err)
+    };
+
+    let db = match Db::open(&config.database_path) {
+        Ok(db) => db,
+        Err(err) => panic!("Could not open database '{}': {:?}", config.database_path.to_string_lossy(), err)
+    };
+
+    let from = match args.from.as_deref().map(|value| parse_date_arg(value, false)).transpose() {
+        Ok(from) => from,
+        Err(err) => panic!("Invalid --from: {}", err)
+    };
+    let to = match args.to.as_deref().map(|value| parse_date_arg(value, true)).transpose() {
+        Ok(to) => to,
+        Err(err) => panic!("Invalid --to: {}", err)
+    };
+
+    let intervals = match db.intervals_in_range(from, to) {
+        Ok(intervals) => intervals,
+        Err(err) => panic!("Could not query database: {:?}", err)
+    };
+
+    let query = report::ReportQuery {
+        by: args.by.into(),
+        exclude_idle: args.exclude_idle,
+    };
+    let entries = report::aggregate(&intervals, &query);
+    print!("{}", report::format_report(&entries, args.format.into()));
+}
+
+// Runs a single sample/categorize/open-or-close-interval step and returns
+// the resulting open interval (or `None` if the sample errored). Factored
+// out of `run` so tests can drive it with `FakeSampler`/`FakeClocks` without
+// looping forever.
+fn tick(
+    sampler: &dyn Sampler,
+    clocks: &dyn Clocks,
+    db: &mut Db,
+    rules: &[Rule],
+    sample_interval: time::Duration,
+    open_interval: Option<OpenInterval>,
+) -> Option<OpenInterval> {
+    match sampler.sample(&sample_interval) {
+        Ok(mut sample) => {
+            sample.category = categorize(rules, &sample);
+            let same_as_open = match &open_interval {
+                Some(open) => open.sample == sample,
+                None => false
+            };
+            if same_as_open {
+                return open_interval;
             }
-            Err(err) => {
-                eprintln!("Error fetching data: {:?}", err);
-                last_sample = None;
+            let now = clocks.now();
+            if let Some(open) = open_interval {
+                close_interval(db, open, now);
             }
+            return Some(OpenInterval { sample, start_ts: now });
         }
-        thread::sleep(sample_interval);
+        Err(err) => {
+            eprintln!("Error fetching data: {:?}", err);
+            if let Some(open) = open_interval {
+                close_interval(db, open, clocks.now());
+            }
+            return None;
+        }
+    }
+}
+
+fn run(
+    sampler: &dyn Sampler,
+    clocks: &dyn Clocks,
+    db: &mut Db,
+    sample_interval: time::Duration,
+    rules: &[Rule],
+) {
+    let mut open_interval: Option<OpenInterval> = None;
+    loop {
+        open_interval = tick(sampler, clocks, db, rules, sample_interval, open_interval);
+        clocks.sleep(sample_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::FakeClocks;
+    use sampler::{FakeSampler, SampleError};
+
+    fn sample(window_title: &str, pid: u32, process_name: &str) -> Sample {
+        return Sample {
+            window_title: window_title.to_string(),
+            pid,
+            process_name: process_name.to_string(),
+            screensaver_active: false,
+            idle: false,
+            category: None,
+        };
+    }
+
+    fn run_ticks(sampler: &FakeSampler, clocks: &FakeClocks, db: &mut Db, count: usize) -> Option<OpenInterval> {
+        let sample_interval = time::Duration::from_secs(5);
+        let mut open_interval = None;
+        for _ in 0..count {
+            open_interval = tick(sampler, clocks, db, &[], sample_interval, open_interval);
+            clocks.sleep(sample_interval);
+        }
+        return open_interval;
+    }
+
+    #[test]
+    fn closes_interval_on_sample_change() {
+        let sampler = FakeSampler::new(vec![
+            Ok(sample("a", 1, "a.bin")),
+            Ok(sample("a", 1, "a.bin")),
+            Ok(sample("b", 2, "b.bin")),
+        ]);
+        let clocks = FakeClocks::new(1000);
+        let mut db = Db::open(path::Path::new(":memory:")).expect("open in-memory db");
+
+        let open_interval = run_ticks(&sampler, &clocks, &mut db, 3);
+
+        let intervals = db.intervals_in_range(None, None).expect("query intervals");
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].window_title, "a");
+        assert_eq!(intervals[0].start_ts, 1000);
+        assert_eq!(intervals[0].end_ts, 1010);
+
+        let open = open_interval.expect("third sample should still be open");
+        assert_eq!(open.sample.window_title, "b");
+        assert_eq!(open.start_ts, 1010);
+    }
+
+    #[test]
+    fn closes_interval_on_sample_error() {
+        let sampler = FakeSampler::new(vec![
+            Ok(sample("a", 1, "a.bin")),
+            Err(SampleError::Io(io::Error::new(io::ErrorKind::Other, "boom"))),
+            Ok(sample("b", 2, "b.bin")),
+        ]);
+        let clocks = FakeClocks::new(2000);
+        let mut db = Db::open(path::Path::new(":memory:")).expect("open in-memory db");
+
+        let open_interval = run_ticks(&sampler, &clocks, &mut db, 3);
+
+        let intervals = db.intervals_in_range(None, None).expect("query intervals");
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].window_title, "a");
+        assert_eq!(intervals[0].start_ts, 2000);
+        assert_eq!(intervals[0].end_ts, 2005);
+
+        let open = open_interval.expect("sample after the error should have opened a new interval");
+        assert_eq!(open.sample.window_title, "b");
+        assert_eq!(open.start_ts, 2010);
     }
 }
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use crate::storage::Interval;
+
+#[derive(Debug, Clone, Copy)]
+pub enum GroupBy {
+    Process,
+    Category,
+    Day,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+pub struct ReportQuery {
+    pub by: GroupBy,
+    pub exclude_idle: bool,
+}
+
+fn group_key(interval: &Interval, by: GroupBy) -> String {
+    return match by {
+        GroupBy::Process => interval.process_name.clone(),
+        GroupBy::Category => interval
+            .category
+            .clone()
+            .unwrap_or_else(|| "uncategorized".to_string()),
+        GroupBy::Day => {
+            let naive = chrono::DateTime::from_timestamp(interval.start_ts as i64, 0)
+                .expect("interval start_ts out of range for a timestamp")
+                .naive_utc();
+            naive.format("%Y-%m-%d").to_string()
+        }
+    };
+}
+
+// Sums interval durations per group key, largest total first.
+pub fn aggregate(intervals: &[Interval], query: &ReportQuery) -> Vec<(String, u64)> {
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    for interval in intervals {
+        if query.exclude_idle && (interval.idle || interval.screensaver) {
+            continue;
+        }
+        let key = group_key(interval, query.by);
+        *totals.entry(key).or_insert(0) += interval.duration;
+    }
+
+    let mut entries: Vec<(String, u64)> = totals.into_iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1));
+    return entries;
+}
+
+#[derive(serde::Serialize)]
+struct ReportEntry<'a> {
+    key: &'a str,
+    seconds: u64,
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    return value.to_string();
+}
+
+pub fn format_report(entries: &[(String, u64)], format: Format) -> String {
+    return match format {
+        Format::Table => {
+            let mut out = String::new();
+            for (key, seconds) in entries {
+                out.push_str(&format!("{:<30} {:>10}s\n", key, seconds));
+            }
+            out
+        }
+        Format::Json => {
+            let items: Vec<ReportEntry> = entries
+                .iter()
+                .map(|(key, seconds)| ReportEntry { key, seconds: *seconds })
+                .collect();
+            format!(
+                "{}\n",
+                serde_json::to_string(&items).expect("ReportEntry is always serializable")
+            )
+        }
+        Format::Csv => {
+            let mut out = String::from("key,seconds\n");
+            for (key, seconds) in entries {
+                out.push_str(&format!("{},{}\n", escape_csv(key), seconds));
+            }
+            out
+        }
+    };
+}
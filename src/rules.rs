@@ -0,0 +1,83 @@
+use crate::sampler::Sample;
+use crate::LoadConfigError;
+
+// Maps a window/process onto a user-defined category or project. Rules are
+// tried in order and the first match wins, the same way bottom's filter
+// tables work.
+#[derive(Debug)]
+pub struct Rule {
+    pub process_name: Option<String>,
+    pub title_regex: Option<regex::Regex>,
+    pub category: String,
+}
+
+impl Rule {
+    fn matches(&self, sample: &Sample) -> bool {
+        let process_matches = match &self.process_name {
+            Some(process_name) => process_name == &sample.process_name,
+            None => true
+        };
+        let title_matches = match &self.title_regex {
+            Some(title_regex) => title_regex.is_match(&sample.window_title),
+            None => true
+        };
+        return process_matches && title_matches;
+    }
+}
+
+pub fn categorize(rules: &[Rule], sample: &Sample) -> Option<String> {
+    for rule in rules {
+        if rule.matches(sample) {
+            return Some(rule.category.clone());
+        }
+    }
+    return None;
+}
+
+fn parse_rule(value: &toml::Value) -> Result<Rule, LoadConfigError> {
+    let table = match value.as_table() {
+        Some(table) => table,
+        None => return Err(LoadConfigError::ParseError {0: "each [[rule]] entry must be a table".to_string()})
+    };
+
+    let process_name = match table.get("process_name") {
+        Some(value) => match value.as_str() {
+            Some(process_name) => Some(process_name.to_string()),
+            None => return Err(LoadConfigError::ParseError {0: "rule.process_name must be a string".to_string()})
+        },
+        None => None
+    };
+
+    let title_regex = match table.get("title_regex") {
+        Some(value) => match value.as_str() {
+            Some(pattern) => match regex::Regex::new(pattern) {
+                Ok(title_regex) => Some(title_regex),
+                Err(err) => return Err(LoadConfigError::ParseError {0: format!("rule.title_regex is not a valid regex: {}", err)})
+            },
+            None => return Err(LoadConfigError::ParseError {0: "rule.title_regex must be a string".to_string()})
+        },
+        None => None
+    };
+
+    let category = match table.get("category") {
+        Some(value) => match value.as_str() {
+            Some(category) => category.to_string(),
+            None => return Err(LoadConfigError::ParseError {0: "rule.category must be a string".to_string()})
+        },
+        None => return Err(LoadConfigError::ParseError {0: "rule must have a category".to_string()})
+    };
+
+    if process_name.is_none() && title_regex.is_none() {
+        return Err(LoadConfigError::ParseError {0: "rule must match on process_name, title_regex, or both".to_string()});
+    }
+
+    return Ok(Rule { process_name, title_regex, category });
+}
+
+pub fn parse_rules(value: &toml::Value) -> Result<Vec<Rule>, LoadConfigError> {
+    let array = match value.as_array() {
+        Some(array) => array,
+        None => return Err(LoadConfigError::ParseError {0: "rule must be an array of tables".to_string()})
+    };
+    return array.iter().map(parse_rule).collect();
+}
@@ -0,0 +1,213 @@
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::idle;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sample {
+    pub window_title: String,
+    pub pid: u32,
+    pub process_name: String,
+    pub screensaver_active: bool,
+    pub idle: bool,
+    // Populated after sampling, by matching `Config`'s rules against the
+    // window title/process name (see the `rules` module).
+    pub category: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SampleError {
+    Io(io::Error),
+    FromUtf8(std::string::FromUtf8Error),
+    ParseInt(std::num::ParseIntError),
+    Dbus(dbus::Error),
+    X11(String),
+    Json(serde_json::Error),
+    // No D-Bus screensaver/idle interface (nor, for idle time, the X11
+    // screensaver extension) responded. The errors of the individual sources
+    // are discarded, so a source that is absent and one that is present but
+    // failing both end up here.
+    NoIdleSource,
+}
+
+impl From<io::Error> for SampleError {
+    fn from(err: io::Error) -> SampleError {
+        return SampleError::Io(err);
+    }
+}
+
+impl From<std::string::FromUtf8Error> for SampleError {
+    fn from(err: std::string::FromUtf8Error) -> SampleError {
+        return SampleError::FromUtf8(err);
+    }
+}
+
+impl From<std::num::ParseIntError> for SampleError {
+    fn from(err: std::num::ParseIntError) -> SampleError {
+        return SampleError::ParseInt(err);
+    }
+}
+
+impl From<serde_json::Error> for SampleError {
+    fn from(err: serde_json::Error) -> SampleError {
+        return SampleError::Json(err);
+    }
+}
+
+// Anything that can produce a `Sample` for the currently active window.
+// Concrete implementations hide the display server (X11, Wayland, ...)
+// behind this so the main loop and tests don't have to care which one runs.
+pub trait Sampler {
+    fn sample(&self, interval: &Duration) -> Result<Sample, SampleError>;
+}
+
+fn get_command_output(command: &str, args: &[&str]) -> Result<String, SampleError> {
+    return Ok(String::from_utf8(
+        Command::new(command)
+            .args(args)
+            .env("LC_ALL", "C") // I will never understand localization of CLI tool output
+            .output()?
+            .stdout,
+    )?
+    .trim()
+    .to_string());
+}
+
+pub struct XdotoolSampler;
+
+impl Sampler for XdotoolSampler {
+    fn sample(&self, interval: &Duration) -> Result<Sample, SampleError> {
+        let window_title = get_command_output("xdotool", &["getactivewindow", "getwindowname"])?;
+        let pid_str = get_command_output("xdotool", &["getactivewindow", "getwindowpid"])?;
+        let pid: u32 = pid_str.parse()?;
+        let process_name = get_command_output("ps", &["-p", &pid_str, "-o", "comm="])?;
+
+        let conn = dbus::blocking::Connection::new_session()?;
+        let screensaver_active = idle::screensaver_active(&conn)?;
+        let idle_time = idle::idle_millis(&conn)? as u128;
+        let idle = idle_time > interval.as_millis();
+
+        return Ok(Sample {
+            window_title,
+            pid,
+            process_name,
+            screensaver_active,
+            idle,
+            category: None,
+        });
+    }
+}
+
+// The subset of `swaymsg -t get_tree -r`'s node schema we need to find the
+// focused container and read its title, pid and app_id/window class.
+#[derive(serde::Deserialize)]
+struct SwayNode {
+    focused: bool,
+    name: Option<String>,
+    pid: Option<i64>,
+    app_id: Option<String>,
+    window_properties: Option<SwayWindowProperties>,
+    #[serde(default)]
+    nodes: Vec<SwayNode>,
+    #[serde(default)]
+    floating_nodes: Vec<SwayNode>,
+}
+
+#[derive(serde::Deserialize)]
+struct SwayWindowProperties {
+    class: Option<String>,
+}
+
+impl SwayNode {
+    fn find_focused(&self) -> Option<&SwayNode> {
+        if self.focused {
+            return Some(self);
+        }
+        for child in self.nodes.iter().chain(self.floating_nodes.iter()) {
+            if let Some(focused) = child.find_focused() {
+                return Some(focused);
+            }
+        }
+        return None;
+    }
+}
+
+// Wayland has no equivalent of `xdotool`; compositors expose the active
+// window (and idle state, on compositors that support it) through their own
+// introspection protocols instead. sway is the one we actually talk to today,
+// via `swaymsg -t get_tree`; other compositors would need their own sampler.
+pub struct WaylandSampler;
+
+impl WaylandSampler {
+    fn focused_window(tree: &str) -> Result<Option<(String, u32, String)>, SampleError> {
+        let root: SwayNode = serde_json::from_str(tree)?;
+        return Ok(root.find_focused().map(|node| {
+            let window_title = node.name.clone().unwrap_or_default();
+            let pid = node.pid.unwrap_or(0) as u32;
+            // Native Wayland clients report `app_id`; XWayland clients only
+            // set `window_properties.class` instead.
+            let process_name = node
+                .app_id
+                .clone()
+                .or_else(|| node.window_properties.as_ref().and_then(|p| p.class.clone()))
+                .unwrap_or_default();
+            (window_title, pid, process_name)
+        }));
+    }
+}
+
+impl Sampler for WaylandSampler {
+    fn sample(&self, interval: &Duration) -> Result<Sample, SampleError> {
+        let tree = get_command_output("swaymsg", &["-t", "get_tree", "-r"])?;
+        let (window_title, pid, process_name) = match WaylandSampler::focused_window(&tree)? {
+            Some(window) => window,
+            None => {
+                return Err(SampleError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no focused window reported by compositor",
+                )))
+            }
+        };
+
+        let conn = dbus::blocking::Connection::new_session()?;
+        let screensaver_active = idle::screensaver_active(&conn)?;
+        let idle_time = idle::idle_millis(&conn)? as u128;
+        let idle = idle_time > interval.as_millis();
+
+        return Ok(Sample {
+            window_title,
+            pid,
+            process_name,
+            screensaver_active,
+            idle,
+            category: None,
+        });
+    }
+}
+
+// Returns a fixed, scripted sequence of samples. Used to drive the main loop
+// in tests without depending on a real X11/Wayland session.
+pub struct FakeSampler {
+    samples: std::cell::RefCell<std::vec::IntoIter<Result<Sample, SampleError>>>,
+}
+
+impl FakeSampler {
+    pub fn new(samples: Vec<Result<Sample, SampleError>>) -> FakeSampler {
+        return FakeSampler {
+            samples: std::cell::RefCell::new(samples.into_iter()),
+        };
+    }
+}
+
+impl Sampler for FakeSampler {
+    fn sample(&self, _interval: &Duration) -> Result<Sample, SampleError> {
+        return match self.samples.borrow_mut().next() {
+            Some(result) => result,
+            None => Err(SampleError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "FakeSampler ran out of scripted samples",
+            ))),
+        };
+    }
+}
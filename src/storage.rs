@@ -0,0 +1,114 @@
+use std::path;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> StorageError {
+        return StorageError::Sqlite(err);
+    }
+}
+
+#[derive(Debug)]
+pub struct Interval {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub duration: u64,
+    pub window_title: String,
+    pub pid: u32,
+    pub process_name: String,
+    pub idle: bool,
+    pub screensaver: bool,
+    pub category: Option<String>,
+}
+
+pub struct Db {
+    conn: rusqlite::Connection,
+}
+
+impl Db {
+    pub fn open(path: &path::Path) -> Result<Db, StorageError> {
+        let conn = rusqlite::Connection::open(path)?;
+        let db = Db { conn };
+        db.migrate()?;
+        return Ok(db);
+    }
+
+    fn migrate(&self) -> Result<(), StorageError> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS intervals (
+                id INTEGER PRIMARY KEY,
+                start_ts INTEGER NOT NULL,
+                end_ts INTEGER NOT NULL,
+                duration INTEGER NOT NULL,
+                window_title TEXT NOT NULL,
+                pid INTEGER NOT NULL,
+                process_name TEXT NOT NULL,
+                idle INTEGER NOT NULL,
+                screensaver INTEGER NOT NULL,
+                category TEXT
+            )",
+            [],
+        )?;
+        return Ok(());
+    }
+
+    // Writes a single closed interval inside its own transaction, so a crash
+    // can lose at most the interval that is currently open in the caller.
+    pub fn record_interval(&mut self, interval: &Interval) -> Result<(), StorageError> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO intervals
+                (start_ts, end_ts, duration, window_title, pid, process_name, idle, screensaver, category)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                interval.start_ts as i64,
+                interval.end_ts as i64,
+                interval.duration as i64,
+                interval.window_title,
+                interval.pid,
+                interval.process_name,
+                interval.idle,
+                interval.screensaver,
+                interval.category,
+            ],
+        )?;
+        tx.commit()?;
+        return Ok(());
+    }
+
+    // Reads back closed intervals, optionally restricted to a time range, for
+    // the `report` subcommand to aggregate.
+    pub fn intervals_in_range(&self, from: Option<u64>, to: Option<u64>) -> Result<Vec<Interval>, StorageError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT start_ts, end_ts, duration, window_title, pid, process_name, idle, screensaver, category
+             FROM intervals
+             WHERE (?1 IS NULL OR end_ts >= ?1) AND (?2 IS NULL OR start_ts <= ?2)
+             ORDER BY start_ts",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![from.map(|ts| ts as i64), to.map(|ts| ts as i64)],
+            |row| {
+                Ok(Interval {
+                    start_ts: row.get::<_, i64>(0)? as u64,
+                    end_ts: row.get::<_, i64>(1)? as u64,
+                    duration: row.get::<_, i64>(2)? as u64,
+                    window_title: row.get(3)?,
+                    pid: row.get(4)?,
+                    process_name: row.get(5)?,
+                    idle: row.get(6)?,
+                    screensaver: row.get(7)?,
+                    category: row.get(8)?,
+                })
+            },
+        )?;
+
+        let mut intervals = Vec::new();
+        for row in rows {
+            intervals.push(row?);
+        }
+        return Ok(intervals);
+    }
+}